@@ -16,4 +16,19 @@ pub enum LedgerCosmosError {
     Exchange(String),
     #[error("{} serde_json error `{0}`", LEDGER_COSMOS_ERROR)]
     Serde(#[from] serde_json::Error),
+    #[error("{} protobuf encoding error `{0}`", LEDGER_COSMOS_ERROR)]
+    Encoding(String),
+    #[error("{} failed to query account `{0}`", LEDGER_COSMOS_ERROR)]
+    AccountQuery(String),
+    #[error("{} broadcast rejected by CheckTx: code {code}, log `{log}`", LEDGER_COSMOS_ERROR)]
+    CheckTx { code: u32, log: String },
+    #[error("{} broadcast rejected by DeliverTx: code {code}, log `{log}`", LEDGER_COSMOS_ERROR)]
+    DeliverTx {
+        code: u32,
+        log: String,
+        /// The sequence to use for the next transaction: CheckTx accepted the
+        /// signed sequence, so this is that sequence plus one, letting callers
+        /// keep their local sequence cache in sync despite the failure.
+        next_sequence: u64,
+    },
 }
@@ -2,7 +2,7 @@
 use byteorder::{LittleEndian, WriteBytesExt};
 use cosmrs::{
     tendermint::PublicKey,
-    tx::{Fee, Msg},
+    tx::{AuthInfo, Body, Fee, Msg, Raw, SignDoc},
 };
 use error::LedgerCosmosError;
 
@@ -18,6 +18,7 @@ use stdtx::amino;
 pub mod error;
 // pub mod jsonrpc;
 pub mod sign_msg;
+pub mod signer;
 pub mod tx_request;
 pub mod tx_signer;
 
@@ -33,6 +34,12 @@ const GET_ADDR_SECP256K1_INS: u8 = 0x04;
 /// Instruction for signing a secp256k1 transaction.
 const SIGN_SECP256K1_INS: u8 = 0x02;
 
+/// `p2` selector for legacy amino JSON sign bytes.
+const SIGN_SECP256K1_P2_AMINO: u8 = 0x00;
+
+/// `p2` selector for protobuf `SIGN_MODE_DIRECT` sign bytes.
+const SIGN_SECP256K1_P2_DIRECT: u8 = 0x01;
+
 pub trait IntoValue: Msg + Serialize {
     fn into_value(self) -> Value;
 }
@@ -64,6 +71,38 @@ pub struct Secp256k1Response {
     pub addr: String,
 }
 
+/// Per-chain parameters for address derivation, so the crate isn't hardcoded
+/// to the Cosmos Hub's `cosmos`/118 defaults.
+#[derive(Debug, Clone)]
+pub struct ChainConfig {
+    /// Bech32 human-readable prefix for account addresses, e.g. `"cosmos"` or `"terra"`.
+    pub hrp: String,
+
+    /// BIP44 coin type, e.g. `118` for the Cosmos Hub.
+    pub coin_type: u32,
+
+    /// Bech32 human-readable prefix for validator operator addresses, if different from `hrp`.
+    pub val_prefix: Option<String>,
+}
+
+impl ChainConfig {
+    /// Build the standard `44'/coin_type'/account'/change/address_index` path for this chain.
+    pub fn derivation_path(&self, account: u32, change: u32, address_index: u32) -> [u32; 5] {
+        [44, self.coin_type, account, change, address_index]
+    }
+}
+
+impl Default for ChainConfig {
+    /// Cosmos Hub defaults: `hrp = "cosmos"`, `coin_type = 118`.
+    fn default() -> Self {
+        ChainConfig {
+            hrp: "cosmos".to_string(),
+            coin_type: 118,
+            val_prefix: Some("cosmosvaloper".to_string()),
+        }
+    }
+}
+
 // #[derive(Debug, Serialize, Deserialize)]
 // pub struct LedgerSignDoc {
 //     pub account_number: u64,
@@ -103,6 +142,11 @@ where
     T::Error: std::error::Error,
 {
     transport: T,
+    chain_config: ChainConfig,
+    /// Default derivation path used by the `CosmosSigner` impl.
+    pub(crate) derivation_path: [u32; 5],
+    /// Default chain ID used by the `CosmosSigner` impl.
+    pub(crate) chain_id: String,
 }
 
 impl<T> App for CosmosApp<T>
@@ -118,8 +162,35 @@ where
     T: Exchange + Send + Sync,
     T::Error: std::error::Error,
 {
-    pub fn new(transport: T) -> Self {
-        CosmosApp { transport }
+    pub fn new(transport: T, chain_id: String) -> Self {
+        Self::new_with_chain_config(transport, ChainConfig::default(), chain_id)
+    }
+
+    pub fn new_with_chain_config(transport: T, chain_config: ChainConfig, chain_id: String) -> Self {
+        let derivation_path = chain_config.derivation_path(0, 0, 0);
+        CosmosApp {
+            transport,
+            chain_config,
+            derivation_path,
+            chain_id,
+        }
+    }
+
+    /// The chain this app is configured to derive addresses and sign for.
+    pub fn chain_config(&self) -> &ChainConfig {
+        &self.chain_config
+    }
+
+    /// Override the derivation path used by the `CosmosSigner` impl.
+    pub fn with_derivation_path(mut self, derivation_path: [u32; 5]) -> Self {
+        self.derivation_path = derivation_path;
+        self
+    }
+
+    /// Override the chain ID used by the `CosmosSigner` impl.
+    pub fn with_chain_id(mut self, chain_id: String) -> Self {
+        self.chain_id = chain_id;
+        self
     }
 
     pub async fn get_cosmos_app_version(&self) -> Result<CosmosAppVersion, LedgerCosmosError> {
@@ -214,10 +285,38 @@ where
         }
     }
 
+    /// Derive addresses for `count` consecutive accounts starting at `account_start`.
+    pub async fn get_addr_secp256k1_range(
+        &self,
+        account_start: u32,
+        count: u32,
+        change: u32,
+        hrp: &str,
+    ) -> Result<Vec<Secp256k1Response>, LedgerCosmosError> {
+        let mut responses = Vec::with_capacity(count as usize);
+        for offset in 0..count {
+            let path = self
+                .chain_config
+                .derivation_path(account_start + offset, change, 0);
+            responses.push(self.get_addr_secp256k1(path, hrp, false).await?);
+        }
+        Ok(responses)
+    }
+
     pub async fn sign_secp256k1(
         &self,
         path: [u32; 5],
         message: &[u8],
+    ) -> Result<Signature, LedgerCosmosError> {
+        self.sign_secp256k1_with_p2(path, message, SIGN_SECP256K1_P2_AMINO)
+            .await
+    }
+
+    async fn sign_secp256k1_with_p2(
+        &self,
+        path: [u32; 5],
+        message: &[u8],
+        p2: u8,
     ) -> Result<Signature, LedgerCosmosError> {
         let mut init_payload: Vec<u8> = Vec::new();
         init_payload
@@ -235,7 +334,7 @@ where
             cla: COSMOS_CLA,
             ins: SIGN_SECP256K1_INS,
             p1: 0x00,
-            p2: 0x00,
+            p2,
             data: init_payload,
         };
         info!("init command: {:#?}", init_command);
@@ -277,7 +376,7 @@ where
         let mut signature = amino::StdSignature::from(res);
 
         signature.pub_key = self
-            .get_addr_secp256k1(derivation_path, "cosmos", false)
+            .get_addr_secp256k1(derivation_path, &self.chain_config.hrp, false)
             .await?
             .public_key
             .to_bytes();
@@ -304,6 +403,52 @@ where
 
         Ok(sign_msg.to_stdtx(signature))
     }
+
+    /// Sign a protobuf `SIGN_MODE_DIRECT` transaction, as used by modern
+    /// Cosmos SDK chains that no longer accept legacy amino JSON.
+    pub async fn sign_direct(
+        &self,
+        derivation_path: [u32; 5],
+        body: Body,
+        auth_info: AuthInfo,
+        chain_id: String,
+        account_number: u64,
+    ) -> Result<Raw, LedgerCosmosError> {
+        info!(
+            "Signing secp256k1 direct
+            derivation_path: {:?}
+            chain_id: {}
+            account_number: {}",
+            derivation_path, chain_id, account_number
+        );
+        let body_bytes = body
+            .into_bytes()
+            .map_err(|e| LedgerCosmosError::Encoding(e.to_string()))?;
+        let auth_info_bytes = auth_info
+            .into_bytes()
+            .map_err(|e| LedgerCosmosError::Encoding(e.to_string()))?;
+
+        let sign_doc = SignDoc {
+            body_bytes: body_bytes.clone(),
+            auth_info_bytes: auth_info_bytes.clone(),
+            chain_id,
+            account_number,
+        };
+        let sign_doc_bytes = sign_doc
+            .into_bytes()
+            .map_err(|e| LedgerCosmosError::Encoding(e.to_string()))?;
+
+        let signature = self
+            .sign_secp256k1_with_p2(derivation_path, &sign_doc_bytes, SIGN_SECP256K1_P2_DIRECT)
+            .await?;
+        info!("res: {:?}", signature);
+
+        Ok(Raw {
+            body_bytes,
+            auth_info_bytes,
+            signatures: vec![signature.to_bytes().to_vec()],
+        })
+    }
 }
 
 fn decompress_pk(compressed_pk: &[u8]) -> Result<PublicKey, LedgerCosmosError> {
@@ -374,7 +519,8 @@ mod tests {
         let device = TransportNativeHID::list_ledgers(&api).next().unwrap();
         let ledger = TransportNativeHID::open_device(&api, device).unwrap();
 
-        let app = CosmosApp::new(ledger);
+        let chain_id = "oasis-1".to_string();
+        let app = CosmosApp::new(ledger, chain_id.clone());
         let derivation_path = [44, 118, 0, 0, 0];
 
         let fee = amino::StdFee {
@@ -386,7 +532,6 @@ mod tests {
         };
 
         let account_number = 123;
-        let chain_id = "oasis-1".to_string();
         let memo = "hello".to_string();
         let sequence = 500;
 
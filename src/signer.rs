@@ -0,0 +1,156 @@
+//! Transport-agnostic signer trait, implemented for `CosmosApp` and for a
+//! software-key signer used in tests.
+
+use async_trait::async_trait;
+use cosmrs::tendermint::PublicKey;
+use k256::ecdsa::{signature::Signer as _, Signature, SigningKey};
+use ledger_transport::Exchange;
+
+use crate::{error::LedgerCosmosError, CosmosApp};
+
+/// A signer capable of reporting its address/public key and producing
+/// secp256k1 signatures over already-serialized sign bytes.
+#[async_trait]
+pub trait CosmosSigner {
+    /// Derive the bech32 address for the given `hrp`.
+    async fn address(&self, hrp: &str) -> Result<String, LedgerCosmosError>;
+
+    /// Fetch the signer's public key.
+    async fn public_key(&self) -> Result<PublicKey, LedgerCosmosError>;
+
+    /// The chain this signer is configured for.
+    fn chain_id(&self) -> &str;
+
+    /// Sign the given sign-doc bytes (amino JSON or protobuf `SignDoc`).
+    async fn sign(&self, sign_doc: &[u8]) -> Result<Signature, LedgerCosmosError>;
+}
+
+#[async_trait]
+impl<T> CosmosSigner for CosmosApp<T>
+where
+    T: Exchange + Send + Sync,
+    T::Error: std::error::Error,
+{
+    async fn address(&self, hrp: &str) -> Result<String, LedgerCosmosError> {
+        Ok(self
+            .get_addr_secp256k1(self.derivation_path, hrp, false)
+            .await?
+            .addr)
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, LedgerCosmosError> {
+        Ok(self
+            .get_addr_secp256k1(self.derivation_path, &self.chain_config().hrp, false)
+            .await?
+            .public_key)
+    }
+
+    fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    /// Sends `sign_doc` to the device as amino JSON sign bytes; protobuf
+    /// `SignDoc` bytes are not accepted here (see `CosmosApp::sign_direct`).
+    async fn sign(&self, sign_doc: &[u8]) -> Result<Signature, LedgerCosmosError> {
+        self.sign_secp256k1(self.derivation_path, sign_doc).await
+    }
+}
+
+/// A software secp256k1 [`CosmosSigner`], so `SignMsg`/`TxSigningRequest`
+/// handling can be unit-tested without a physical Ledger attached.
+pub struct SoftwareCosmosSigner {
+    signing_key: SigningKey,
+    chain_id: String,
+}
+
+impl SoftwareCosmosSigner {
+    pub fn new(signing_key: SigningKey, chain_id: String) -> Self {
+        Self {
+            signing_key,
+            chain_id,
+        }
+    }
+}
+
+#[async_trait]
+impl CosmosSigner for SoftwareCosmosSigner {
+    async fn address(&self, hrp: &str) -> Result<String, LedgerCosmosError> {
+        let verifying_key = *self.signing_key.verifying_key();
+        cosmrs::crypto::PublicKey::from(verifying_key)
+            .account_id(hrp)
+            .map(|id| id.to_string())
+            .map_err(|_| LedgerCosmosError::InvalidAddress)
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, LedgerCosmosError> {
+        let compressed = self.signing_key.verifying_key().to_sec1_bytes();
+        PublicKey::from_raw_secp256k1(&compressed).ok_or(LedgerCosmosError::InvalidAddress)
+    }
+
+    fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    async fn sign(&self, sign_doc: &[u8]) -> Result<Signature, LedgerCosmosError> {
+        Ok(self.signing_key.sign(sign_doc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rand_core::OsRng;
+    use stdtx::amino::{self, types::Coin};
+
+    use super::*;
+    use crate::{sign_msg::SignMsg, tx_request::TxSigningRequest};
+
+    #[tokio::test]
+    async fn test_software_signer_signs_sign_msg() {
+        let signing_key = SigningKey::random(&mut OsRng);
+        let signer = SoftwareCosmosSigner::new(signing_key, "test-1".to_string());
+
+        const SCHEMA: &str = r#"
+            namespace = "core/StdTx"
+            acc_prefix = "cosmos"
+            val_prefix = "cosmosvaloper"
+
+            [[definition]]
+            type_name = "cosmos-sdk/MsgSend"
+            fields = [
+                { name = "amount", type = "sdk.Coins" },
+                { name = "from_address", type = "sdk.AccAddress" },
+                { name = "to_address", type = "sdk.AccAddress" },
+            ]
+        "#;
+        let schema = amino::Schema::from_str(SCHEMA).unwrap();
+        let tx_builder = amino::Builder::new(schema, "test-1".to_string(), 1);
+
+        let signing_request = TxSigningRequest {
+            chain_id: "test-1".to_string(),
+            fee: amino::StdFee {
+                amount: vec![Coin {
+                    denom: "uatom".into(),
+                    amount: "5".into(),
+                }],
+                gas: 200_000,
+            },
+            memo: "".to_string(),
+            msgs: vec![serde_json::json!({
+                "type": "cosmos-sdk/MsgSend",
+                "value": {
+                    "amount": [{ "amount": "1", "denom": "uatom" }],
+                    "from_address": "cosmos1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqlgd0k9",
+                    "to_address": "cosmos1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqlgd0k9",
+                },
+            })],
+        };
+
+        let sign_msg = SignMsg::new(&signing_request, &tx_builder, 0).unwrap();
+        let signature = signer.sign(sign_msg.sign_bytes()).await.unwrap();
+
+        assert_eq!(signer.chain_id(), "test-1");
+        assert_eq!(signature.to_bytes().len(), 64);
+    }
+}
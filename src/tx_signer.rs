@@ -1,108 +1,253 @@
 use log::{debug, info};
-// pub use tx_request::TxSigningRequest;
 
 use cosmrs::{
+    proto::cosmos::auth::v1beta1::{BaseAccount, QueryAccountRequest, QueryAccountResponse},
     rpc::{Client, HttpClient},
-    tendermint,
+    tendermint, tx,
 };
+use ledger_transport::Exchange;
+use prost::Message;
 use stdtx::amino;
 use subtle_encoding::hex;
 
-use crate::{error::LedgerCosmosError, sign_msg::SignMsg};
-
-// / Broadcast signed transaction to the Tendermint P2P network via RPC
-// async fn broadcast_tx(
-//     client: HttpClient,
-//     sign_msg: SignMsg,
-//     sequence: u64,
-// ) -> Result<(), LedgerCosmosError> {
-//     let tx = sign_tx(&sign_msg)?;
-
-//     let amino_tx = tendermint_rpc::abci::Transaction::from(
-//         tx.to_amino_bytes(self.tx_builder.schema().namespace()),
-//     );
-
-//     let amino_tx_hex =
-//         String::from_utf8(hex::encode(amino_tx.as_ref())).expect("hex should always be UTF-8");
-
-//     info!(
-//         "[{}] broadcasting TX: {}",
-//         self.chain_id,
-//         amino_tx_hex.to_ascii_uppercase()
-//     );
-
-//     let response = match self.rpc_client.broadcast_tx_commit(amino_tx).await {
-//         Ok(resp) => {
-//             self.last_tx = LastTx::Response(Box::new(resp.clone()));
-//             resp
-//         }
-//         Err(e) => {
-//             self.last_tx = LastTx::Error(e.clone());
-//             return Err(e.into());
-//         }
-//     };
-
-//     if response.check_tx.code.is_err() {
-//         fail!(
-//             ErrorKind::TendermintError,
-//             "TX broadcast failed: {} (CheckTx code={})",
-//             response.check_tx.log,
-//             response.check_tx.code.value(),
-//         );
-//     }
-
-//     // If CheckTx succeeds the sequence number always needs to be
-//     // incremented, even if DeliverTx subsequently fails
-//     self.seq_file.persist(sequence.checked_add(1).unwrap())?;
-
-//     if response.deliver_tx.code.is_err() {
-//         fail!(
-//             ErrorKind::TendermintError,
-//             "TX broadcast failed: {} (DeliverTx code={}, hash={})",
-//             response.deliver_tx.log,
-//             response.deliver_tx.code.value(),
-//             response.hash
-//         );
-//     }
-
-//     info!(
-//         "[{}] successfully broadcast TX {} (shash={})",
-//         self.chain_id,
-//         self.seq_file.sequence(),
-//         response.hash
-//     );
-
-//     Ok(())
-// }
-
-// fn sign_tx(sign_msg: &SignMsg) -> Result<amino::StdTx, LedgerCosmosError> {
-//     let mut signature = amino::StdSignature::from(sign(sign_msg.sign_bytes())?);
-
-//     signature.pub_key = chain
-//         .keyring
-//         .get_account_pubkey(account_id)
-//         .expect("missing account key")
-//         .to_bytes();
-
-//     let msg_type_info = sign_msg
-//         .msg_types()
-//         .iter()
-//         .map(|ty| ty.to_string())
-//         .collect::<Vec<_>>()
-//         .join(", ");
-
-//     let address = self
-//         .address
-//         .to_bech32(self.tx_builder.schema().acc_prefix());
-
-//     info!(
-//         "[{}] signed TX {} for {} ({} msgs total; types: {})",
-//         self.chain_id,
-//         self.seq_file.sequence(),
-//         address,
-//         sign_msg.msgs().len(),
-//         msg_type_info,
-//     );
-
-//     Ok(sign_msg.to_stdtx(signature))
-// }
+use crate::{error::LedgerCosmosError, sign_msg::SignMsg, tx_request::TxSigningRequest, CosmosApp};
+
+/// Broadcasts signed transactions and resolves account/sequence numbers.
+pub struct TxBroadcaster {
+    client: HttpClient,
+}
+
+impl TxBroadcaster {
+    pub fn new(client: HttpClient) -> Self {
+        Self { client }
+    }
+
+    /// Query the chain's auth module for `address`'s current account number
+    /// and sequence.
+    pub async fn account(&self, address: &str) -> Result<(u64, u64), LedgerCosmosError> {
+        let request = QueryAccountRequest {
+            address: address.to_string(),
+        };
+        let response = self
+            .client
+            .abci_query(
+                Some("/cosmos.auth.v1beta1.Query/Account".to_string()),
+                request.encode_to_vec(),
+                None,
+                false,
+            )
+            .await
+            .map_err(|e| LedgerCosmosError::AccountQuery(e.to_string()))?;
+
+        let query_response = QueryAccountResponse::decode(response.value.as_slice())
+            .map_err(|e| LedgerCosmosError::AccountQuery(e.to_string()))?;
+        let account = query_response
+            .account
+            .ok_or_else(|| LedgerCosmosError::AccountQuery("empty account response".into()))?;
+        let base_account = BaseAccount::decode(account.value.as_slice())
+            .map_err(|e| LedgerCosmosError::AccountQuery(e.to_string()))?;
+
+        Ok((base_account.account_number, base_account.sequence))
+    }
+
+    /// Resolve `address`'s account number and sequence, sign `req` for it
+    /// with `app`, and broadcast the result, so the caller never hand-supplies
+    /// an account number or sequence. Returns the sequence to persist for the
+    /// next transaction.
+    pub async fn sign_and_broadcast<T>(
+        &self,
+        app: &CosmosApp<T>,
+        derivation_path: [u32; 5],
+        address: &str,
+        schema: amino::Schema,
+        req: &TxSigningRequest,
+    ) -> Result<(tendermint::Hash, u64), LedgerCosmosError>
+    where
+        T: Exchange + Send + Sync,
+        T::Error: std::error::Error,
+    {
+        let (account_number, sequence) = self.account(address).await?;
+        let tx_builder = amino::Builder::new(schema, req.chain_id.clone(), account_number);
+
+        let sign_msg = SignMsg::new(req, &tx_builder, sequence)?;
+        let tx = app.sign(derivation_path, sign_msg).await?;
+
+        self.broadcast_stdtx(&tx, tx_builder.schema().namespace(), sequence)
+            .await
+    }
+
+    /// As [`Self::sign_and_broadcast`], but for `CosmosApp::sign_direct`: the
+    /// resolved sequence is set on the first signer info of `auth_info` and the
+    /// resolved account number is passed to `sign_direct`, before signing and
+    /// broadcasting. `body` is used as given.
+    pub async fn sign_and_broadcast_direct<T>(
+        &self,
+        app: &CosmosApp<T>,
+        derivation_path: [u32; 5],
+        address: &str,
+        chain_id: String,
+        body: tx::Body,
+        mut auth_info: tx::AuthInfo,
+    ) -> Result<(tendermint::Hash, u64), LedgerCosmosError>
+    where
+        T: Exchange + Send + Sync,
+        T::Error: std::error::Error,
+    {
+        let (account_number, sequence) = self.account(address).await?;
+        if let Some(signer_info) = auth_info.signer_infos.first_mut() {
+            signer_info.sequence = sequence;
+        }
+
+        let tx = app
+            .sign_direct(derivation_path, body, auth_info, chain_id, account_number)
+            .await?;
+
+        self.broadcast_raw(&tx, sequence).await
+    }
+
+    /// Broadcast an amino-encoded `StdTx`, e.g. one produced by `CosmosApp::sign`.
+    pub async fn broadcast_stdtx(
+        &self,
+        tx: &amino::StdTx,
+        namespace: &str,
+        sequence: u64,
+    ) -> Result<(tendermint::Hash, u64), LedgerCosmosError> {
+        self.broadcast_tx_bytes(tx.to_amino_bytes(namespace), sequence)
+            .await
+    }
+
+    /// Broadcast a protobuf-encoded `Raw` tx, e.g. one produced by `CosmosApp::sign_direct`.
+    pub async fn broadcast_raw(
+        &self,
+        tx: &tx::Raw,
+        sequence: u64,
+    ) -> Result<(tendermint::Hash, u64), LedgerCosmosError> {
+        let tx_bytes = tx
+            .to_bytes()
+            .map_err(|e| LedgerCosmosError::Encoding(e.to_string()))?;
+        self.broadcast_tx_bytes(tx_bytes, sequence).await
+    }
+
+    async fn broadcast_tx_bytes(
+        &self,
+        tx_bytes: Vec<u8>,
+        sequence: u64,
+    ) -> Result<(tendermint::Hash, u64), LedgerCosmosError> {
+        let tx_hex =
+            String::from_utf8(hex::encode(&tx_bytes)).expect("hex should always be UTF-8");
+        info!("broadcasting TX: {}", tx_hex.to_ascii_uppercase());
+
+        let response = self
+            .client
+            .broadcast_tx_commit(tendermint::abci::Transaction::from(tx_bytes))
+            .await
+            .map_err(|e| LedgerCosmosError::Exchange(e.to_string()))?;
+
+        let result = evaluate_broadcast(
+            &response.check_tx,
+            &response.deliver_tx,
+            response.hash,
+            sequence,
+        );
+        if result.is_ok() {
+            debug!("broadcast TX {} committed", response.hash);
+        }
+        result
+    }
+}
+
+/// Turn a CheckTx/DeliverTx pair into a `(hash, next_sequence)` result or the
+/// matching [`LedgerCosmosError`] variant. Split out from
+/// `TxBroadcaster::broadcast_tx_bytes` so the branching can be unit-tested
+/// without a live RPC endpoint.
+fn evaluate_broadcast(
+    check_tx: &tendermint::abci::response::CheckTx,
+    deliver_tx: &tendermint::abci::response::DeliverTx,
+    hash: tendermint::Hash,
+    sequence: u64,
+) -> Result<(tendermint::Hash, u64), LedgerCosmosError> {
+    if check_tx.code.is_err() {
+        return Err(LedgerCosmosError::CheckTx {
+            code: check_tx.code.value(),
+            log: check_tx.log.to_string(),
+        });
+    }
+
+    // CheckTx succeeded, so the sequence must advance even if DeliverTx
+    // subsequently fails.
+    let next_sequence = sequence.checked_add(1).unwrap();
+
+    if deliver_tx.code.is_err() {
+        return Err(LedgerCosmosError::DeliverTx {
+            code: deliver_tx.code.value(),
+            log: deliver_tx.log.to_string(),
+            next_sequence,
+        });
+    }
+
+    Ok((hash, next_sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use tendermint::abci::{response, Code};
+
+    use super::*;
+
+    fn fake_responses(
+        check_code: u32,
+        deliver_code: u32,
+    ) -> (response::CheckTx, response::DeliverTx, tendermint::Hash) {
+        let code = |value: u32| {
+            if value == 0 {
+                Code::Ok
+            } else {
+                Code::Err(value.try_into().unwrap())
+            }
+        };
+        let check_tx = response::CheckTx {
+            code: code(check_code),
+            log: "check".to_string(),
+            ..Default::default()
+        };
+        let deliver_tx = response::DeliverTx {
+            code: code(deliver_code),
+            log: "deliver".to_string(),
+            ..Default::default()
+        };
+        (check_tx, deliver_tx, tendermint::Hash::None)
+    }
+
+    #[test]
+    fn test_evaluate_broadcast_check_tx_failure() {
+        let (check_tx, deliver_tx, hash) = fake_responses(1, 0);
+        let err = evaluate_broadcast(&check_tx, &deliver_tx, hash, 5).unwrap_err();
+        assert!(matches!(err, LedgerCosmosError::CheckTx { code: 1, .. }));
+    }
+
+    #[test]
+    fn test_evaluate_broadcast_deliver_tx_failure_carries_next_sequence() {
+        let (check_tx, deliver_tx, hash) = fake_responses(0, 2);
+        let err = evaluate_broadcast(&check_tx, &deliver_tx, hash, 5).unwrap_err();
+        match err {
+            LedgerCosmosError::DeliverTx {
+                code,
+                next_sequence,
+                ..
+            } => {
+                assert_eq!(code, 2);
+                assert_eq!(next_sequence, 6);
+            }
+            other => panic!("expected DeliverTx error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_broadcast_success() {
+        let (check_tx, deliver_tx, hash) = fake_responses(0, 0);
+        let (returned_hash, next_sequence) =
+            evaluate_broadcast(&check_tx, &deliver_tx, hash, 5).unwrap();
+        assert_eq!(returned_hash, hash);
+        assert_eq!(next_sequence, 6);
+    }
+}